@@ -0,0 +1,481 @@
+// SPDX-FileCopyrightText: 2020 Robin Krahl <robin.krahl@ireas.org>
+// SPDX-License-Identifier: Apache-2.0 or MIT
+
+//! This crate provides a generic data model for styled text that is not tied to a particular
+//! terminal library.  A [`StyledStr`][] or [`StyledString`][] pairs a string with an optional
+//! [`Style`][] -- a foreground and background [`Color`][] plus a set of [`Effect`][]s such as
+//! bold or italic -- and the format-specific modules in this crate convert that style into the
+//! types used by the supported terminal libraries.
+//!
+//! Currently, this crate supports [`termion`][].  Each integration is gated behind a feature
+//! flag of the same name.
+//!
+//! [`termion`]: https://docs.rs/termion
+
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+#[cfg(feature = "termion")]
+pub mod termion;
+
+use enumset::{EnumSet, EnumSetType};
+
+/// One of the eight basic ANSI colors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum AnsiColor {
+    /// Black.
+    Black,
+    /// Red.
+    Red,
+    /// Green.
+    Green,
+    /// Yellow.
+    Yellow,
+    /// Blue.
+    Blue,
+    /// Magenta.
+    Magenta,
+    /// Cyan.
+    Cyan,
+    /// White.
+    White,
+}
+
+/// The two shades of the eight basic ANSI colors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum AnsiMode {
+    /// The dark shade, i.e. the eight basic ANSI colors 0-7.
+    Dark,
+    /// The light shade, i.e. the eight bright ANSI colors 8-15.
+    Light,
+}
+
+/// A color that can be used as the foreground or background color of a [`Style`][].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Color {
+    /// One of the 16 basic ANSI colors.
+    Ansi {
+        /// The color.
+        color: AnsiColor,
+        /// The shade of the color.
+        mode: AnsiMode,
+    },
+    /// A 24-bit truecolor value.
+    Rgb {
+        /// The red component.
+        r: u8,
+        /// The green component.
+        g: u8,
+        /// The blue component.
+        b: u8,
+    },
+    /// One of the 256 colors of the extended ANSI color palette, consisting of the 16 basic
+    /// ANSI colors, a 6x6x6 color cube and 24 grayscale steps.
+    Ans256 {
+        /// The index of the color in the 256-color palette.
+        index: u8,
+    },
+}
+
+impl Color {
+    /// Converts this color to the given color depth.
+    ///
+    /// [`Color::Rgb`][] values are down-converted to the nearest representable color if `depth`
+    /// is [`ColorDepth::Ansi256`][] or [`ColorDepth::Ansi16`][], using the squared Euclidean
+    /// distance in RGB space to pick the closest candidate.  Colors that are already
+    /// representable at `depth` -- including non-truecolor colors, which are never down-converted
+    /// further -- are returned unchanged.
+    pub fn to_depth(self, depth: ColorDepth) -> Color {
+        match (self, depth) {
+            (_, ColorDepth::TrueColor) => self,
+            (Color::Rgb { r, g, b }, ColorDepth::Ansi256) => nearest_ans256(r, g, b),
+            (Color::Rgb { r, g, b }, ColorDepth::Ansi16) => nearest_ansi16(r, g, b),
+            (color, _) => color,
+        }
+    }
+}
+
+/// The color depth supported by a terminal, used to down-convert [`Color::Rgb`][] when the
+/// terminal cannot display 24-bit truecolor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ColorDepth {
+    /// 24-bit truecolor: colors are rendered as-is.
+    TrueColor,
+    /// The 256-color palette: [`Color::Rgb`][] is down-converted to the nearest [`Color::Ans256`][].
+    Ansi256,
+    /// The 16 basic ANSI colors: [`Color::Rgb`][] is down-converted to the nearest
+    /// [`Color::Ansi`][].
+    Ansi16,
+}
+
+/// The six per-channel levels of the xterm 6x6x6 color cube.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Returns the square of the difference between two channel values.
+fn squared_diff(a: u8, b: u8) -> u32 {
+    let diff = i32::from(a) - i32::from(b);
+    (diff * diff) as u32
+}
+
+/// Returns the squared Euclidean distance between two RGB colors.
+fn squared_distance(r1: u8, g1: u8, b1: u8, r2: u8, g2: u8, b2: u8) -> u32 {
+    squared_diff(r1, r2) + squared_diff(g1, g2) + squared_diff(b1, b2)
+}
+
+/// Returns the index into [`CUBE_LEVELS`][] closest to `value`.
+fn nearest_cube_level(value: u8) -> usize {
+    CUBE_LEVELS
+        .iter()
+        .enumerate()
+        .min_by_key(|&(_, &level)| squared_diff(value, level))
+        .map(|(index, _)| index)
+        .expect("CUBE_LEVELS is non-empty")
+}
+
+/// Finds the color of the 256-color palette nearest to the given truecolor value, considering
+/// both the 6x6x6 color cube and the 24-step grayscale ramp.
+fn nearest_ans256(r: u8, g: u8, b: u8) -> Color {
+    let r_level = nearest_cube_level(r);
+    let g_level = nearest_cube_level(g);
+    let b_level = nearest_cube_level(b);
+    let cube_index = 16 + 36 * r_level + 6 * g_level + b_level;
+    let cube_distance = squared_distance(
+        r,
+        g,
+        b,
+        CUBE_LEVELS[r_level],
+        CUBE_LEVELS[g_level],
+        CUBE_LEVELS[b_level],
+    );
+
+    let (gray_index, gray_distance) = (0..24u8)
+        .map(|n| {
+            let value = 8 + 10 * n;
+            (232 + n, squared_distance(r, g, b, value, value, value))
+        })
+        .min_by_key(|&(_, distance)| distance)
+        .expect("the grayscale ramp has 24 steps");
+
+    if gray_distance < cube_distance {
+        Color::Ans256 { index: gray_index }
+    } else {
+        Color::Ans256 {
+            index: cube_index as u8,
+        }
+    }
+}
+
+/// The canonical RGB values of the 16 basic ANSI colors, as used by xterm.
+const ANSI16_PALETTE: [(AnsiMode, AnsiColor, u8, u8, u8); 16] = [
+    (AnsiMode::Dark, AnsiColor::Black, 0, 0, 0),
+    (AnsiMode::Dark, AnsiColor::Red, 128, 0, 0),
+    (AnsiMode::Dark, AnsiColor::Green, 0, 128, 0),
+    (AnsiMode::Dark, AnsiColor::Yellow, 128, 128, 0),
+    (AnsiMode::Dark, AnsiColor::Blue, 0, 0, 128),
+    (AnsiMode::Dark, AnsiColor::Magenta, 128, 0, 128),
+    (AnsiMode::Dark, AnsiColor::Cyan, 0, 128, 128),
+    (AnsiMode::Dark, AnsiColor::White, 192, 192, 192),
+    (AnsiMode::Light, AnsiColor::Black, 128, 128, 128),
+    (AnsiMode::Light, AnsiColor::Red, 255, 0, 0),
+    (AnsiMode::Light, AnsiColor::Green, 0, 255, 0),
+    (AnsiMode::Light, AnsiColor::Yellow, 255, 255, 0),
+    (AnsiMode::Light, AnsiColor::Blue, 0, 0, 255),
+    (AnsiMode::Light, AnsiColor::Magenta, 255, 0, 255),
+    (AnsiMode::Light, AnsiColor::Cyan, 0, 255, 255),
+    (AnsiMode::Light, AnsiColor::White, 255, 255, 255),
+];
+
+/// Finds the color of the 16 basic ANSI colors nearest to the given truecolor value.
+fn nearest_ansi16(r: u8, g: u8, b: u8) -> Color {
+    let (mode, color, ..) = ANSI16_PALETTE
+        .iter()
+        .copied()
+        .min_by_key(|&(_, _, pr, pg, pb)| squared_distance(r, g, b, pr, pg, pb))
+        .expect("ANSI16_PALETTE is non-empty");
+    Color::Ansi { color, mode }
+}
+
+/// A text effect, such as bold or italic text.
+#[derive(EnumSetType, Debug)]
+pub enum Effect {
+    /// Bold text.
+    Bold,
+    /// Italic text.
+    Italic,
+    /// Underlined text.
+    Underline,
+    /// Struck-through text.
+    Strikethrough,
+    /// Dimmed (faint) text.
+    Dim,
+    /// Text with the foreground and background colors swapped.
+    Reverse,
+    /// Blinking text.
+    Blink,
+    /// Hidden (invisible) text.
+    Hidden,
+}
+
+/// A set of style attributes -- a foreground color, a background color and a set of text
+/// effects -- that can be applied to a string.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    effects: EnumSet<Effect>,
+}
+
+impl Style {
+    /// Creates a new, empty style.
+    pub fn new() -> Style {
+        Style::default()
+    }
+
+    /// Sets the foreground color of this style.
+    pub fn fg(mut self, color: Color) -> Style {
+        self.fg = Some(color);
+        self
+    }
+
+    /// Sets the background color of this style.
+    pub fn bg(mut self, color: Color) -> Style {
+        self.bg = Some(color);
+        self
+    }
+
+    /// Adds a text effect to this style.
+    pub fn effect(mut self, effect: Effect) -> Style {
+        self.effects |= effect;
+        self
+    }
+
+    /// Adds the bold text effect to this style.
+    pub fn bold(self) -> Style {
+        self.effect(Effect::Bold)
+    }
+
+    /// Adds the italic text effect to this style.
+    pub fn italic(self) -> Style {
+        self.effect(Effect::Italic)
+    }
+
+    /// Adds the underline text effect to this style.
+    pub fn underline(self) -> Style {
+        self.effect(Effect::Underline)
+    }
+
+    /// Adds the strikethrough text effect to this style.
+    pub fn strikethrough(self) -> Style {
+        self.effect(Effect::Strikethrough)
+    }
+
+    /// Adds the dim text effect to this style.
+    pub fn dim(self) -> Style {
+        self.effect(Effect::Dim)
+    }
+
+    /// Adds the reverse text effect to this style.
+    pub fn reverse(self) -> Style {
+        self.effect(Effect::Reverse)
+    }
+
+    /// Adds the blink text effect to this style.
+    pub fn blink(self) -> Style {
+        self.effect(Effect::Blink)
+    }
+
+    /// Adds the hidden text effect to this style.
+    pub fn hidden(self) -> Style {
+        self.effect(Effect::Hidden)
+    }
+}
+
+/// A string with an optional [`Style`][].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StyledStr<'a> {
+    s: &'a str,
+    style: Option<Style>,
+}
+
+impl<'a> StyledStr<'a> {
+    /// Creates a new string without a style.
+    pub fn plain(s: &'a str) -> StyledStr<'a> {
+        StyledStr { s, style: None }
+    }
+
+    /// Creates a new string with the given style.
+    pub fn styled(s: &'a str, style: Style) -> StyledStr<'a> {
+        StyledStr {
+            s,
+            style: Some(style),
+        }
+    }
+
+    /// Sets the foreground color of this string.
+    pub fn fg(mut self, color: Color) -> Self {
+        self.style = Some(self.style.unwrap_or_default().fg(color));
+        self
+    }
+
+    /// Sets the background color of this string.
+    pub fn bg(mut self, color: Color) -> Self {
+        self.style = Some(self.style.unwrap_or_default().bg(color));
+        self
+    }
+
+    /// Adds the bold text effect to this string.
+    pub fn bold(mut self) -> Self {
+        self.style = Some(self.style.unwrap_or_default().bold());
+        self
+    }
+
+    /// Adds the italic text effect to this string.
+    pub fn italic(mut self) -> Self {
+        self.style = Some(self.style.unwrap_or_default().italic());
+        self
+    }
+
+    /// Adds the underline text effect to this string.
+    pub fn underline(mut self) -> Self {
+        self.style = Some(self.style.unwrap_or_default().underline());
+        self
+    }
+
+    /// Adds the strikethrough text effect to this string.
+    pub fn strikethrough(mut self) -> Self {
+        self.style = Some(self.style.unwrap_or_default().strikethrough());
+        self
+    }
+
+    /// Adds the dim text effect to this string.
+    pub fn dim(mut self) -> Self {
+        self.style = Some(self.style.unwrap_or_default().dim());
+        self
+    }
+
+    /// Adds the reverse text effect to this string.
+    pub fn reverse(mut self) -> Self {
+        self.style = Some(self.style.unwrap_or_default().reverse());
+        self
+    }
+
+    /// Adds the blink text effect to this string.
+    pub fn blink(mut self) -> Self {
+        self.style = Some(self.style.unwrap_or_default().blink());
+        self
+    }
+
+    /// Adds the hidden text effect to this string.
+    pub fn hidden(mut self) -> Self {
+        self.style = Some(self.style.unwrap_or_default().hidden());
+        self
+    }
+}
+
+impl<'a> From<&'a str> for StyledStr<'a> {
+    fn from(s: &'a str) -> StyledStr<'a> {
+        StyledStr::plain(s)
+    }
+}
+
+impl<'a> From<&'a StyledString> for StyledStr<'a> {
+    fn from(s: &'a StyledString) -> StyledStr<'a> {
+        StyledStr {
+            s: &s.s,
+            style: s.style,
+        }
+    }
+}
+
+/// An owned string with an optional [`Style`][].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StyledString {
+    s: String,
+    style: Option<Style>,
+}
+
+impl StyledString {
+    /// Creates a new string without a style.
+    pub fn plain(s: impl Into<String>) -> StyledString {
+        StyledString {
+            s: s.into(),
+            style: None,
+        }
+    }
+
+    /// Creates a new string with the given style.
+    pub fn styled(s: impl Into<String>, style: Style) -> StyledString {
+        StyledString {
+            s: s.into(),
+            style: Some(style),
+        }
+    }
+}
+
+impl From<StyledStr<'_>> for StyledString {
+    fn from(s: StyledStr<'_>) -> StyledString {
+        StyledString {
+            s: s.s.to_owned(),
+            style: s.style,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn true_color_depth_is_a_no_op() {
+        let color = Color::Rgb {
+            r: 12,
+            g: 34,
+            b: 56,
+        };
+        assert_eq!(color.to_depth(ColorDepth::TrueColor), color);
+    }
+
+    #[test]
+    fn nearest_ans256_exact_cube_color_round_trips() {
+        // r=0 (level 0), g=95 (level 1), b=135 (level 2) sits exactly on the color cube.
+        let color = Color::Rgb {
+            r: 0,
+            g: 95,
+            b: 135,
+        }
+        .to_depth(ColorDepth::Ansi256);
+        assert_eq!(color, Color::Ans256 { index: 16 + 6 + 2 });
+    }
+
+    #[test]
+    fn nearest_ans256_prefers_gray_when_closer() {
+        // 128 is an exact grayscale step (8 + 10 * 12) that falls between two cube levels.
+        let color = Color::Rgb {
+            r: 128,
+            g: 128,
+            b: 128,
+        }
+        .to_depth(ColorDepth::Ansi256);
+        assert_eq!(color, Color::Ans256 { index: 232 + 12 });
+    }
+
+    #[test]
+    fn nearest_ansi16_matches_canonical_colors() {
+        let color = Color::Rgb { r: 0, g: 0, b: 0 }.to_depth(ColorDepth::Ansi16);
+        assert_eq!(
+            color,
+            Color::Ansi {
+                color: AnsiColor::Black,
+                mode: AnsiMode::Dark,
+            }
+        );
+
+        let color = Color::Rgb { r: 255, g: 0, b: 0 }.to_depth(ColorDepth::Ansi16);
+        assert_eq!(
+            color,
+            Color::Ansi {
+                color: AnsiColor::Red,
+                mode: AnsiMode::Light,
+            }
+        );
+    }
+}
@@ -15,7 +15,16 @@
 //! using its [`Display`][] implementation.
 //!
 //! Alternatively, you can use the [`render`][] function to render a single string and the
-//! [`render_iter`][] function to render an iterator over strings.
+//! [`render_iter`][] function to render an iterator over strings.  [`render_iter`][] resets the
+//! formatting between every piece; if you are rendering many consecutive pieces that often share
+//! styling, [`render_iter_diff`][] emits only the difference between each piece's style and the
+//! previous one, which produces fewer escape sequences for the same visible output.
+//!
+//! [`render_with`][] and [`render_iter_with`][] work like [`render`][] and [`render_iter`][], but
+//! take a [`ColorDepth`][] and down-convert [`Color::Rgb`][] colors for terminals that don't
+//! support 24-bit truecolor.  [`detect_color_depth`][] inspects the environment to pick the
+//! right depth for a given stream, and [`render_auto`][] combines the two so a program can just
+//! call one function and get correctly-degraded output on whatever terminal it's running under.
 //!
 //! Note that this implementation always uses [`termion::style::Reset`][] to clear the formatting
 //! instead of [`termion::style::NoBold`][] etc. for compatibility with terminals that don’t
@@ -59,17 +68,26 @@
 //! [`StyledString`]: ../struct.StyledString.html
 //! [`render`]: fn.render.html
 //! [`render_iter`]: fn.render_iter.html
+//! [`render_iter_diff`]: fn.render_iter_diff.html
+//! [`render_with`]: fn.render_with.html
+//! [`render_iter_with`]: fn.render_iter_with.html
+//! [`render_auto`]: fn.render_auto.html
+//! [`detect_color_depth`]: fn.detect_color_depth.html
+//! [`ColorDepth`]: ../enum.ColorDepth.html
+//! [`Color::Rgb`]: ../enum.Color.html#variant.Rgb
 //! [`Termion`]: trait.Termion.html
 //! [`Termion::termion`]: trait.Termion.html#tymethod.termion
 //! [`TermionStr`]: struct.TermionStr.html
 
 use std::borrow;
+use std::env;
 use std::fmt;
 use std::io;
+use std::os::unix::io::AsRawFd;
 
 use termion::{color, style};
 
-use crate::{AnsiColor, AnsiMode, Color, Effect, Style, StyledStr, StyledString};
+use crate::{AnsiColor, AnsiMode, Color, ColorDepth, Effect, Style, StyledStr, StyledString};
 
 /// A styled string that can be rendered using `termion`.
 ///
@@ -157,6 +175,7 @@ fn get_bg(color: Color) -> borrow::Cow<'static, str> {
     match color {
         Color::Ansi { color, mode } => get_ansi_bg(color, mode).into(),
         Color::Rgb { r, g, b } => color::Rgb(r, g, b).bg_string().into(),
+        Color::Ans256 { index } => color::AnsiValue(index).bg_string().into(),
     }
 }
 
@@ -188,6 +207,7 @@ fn get_fg(color: Color) -> borrow::Cow<'static, str> {
     match color {
         Color::Ansi { color, mode } => get_ansi_fg(color, mode).into(),
         Color::Rgb { r, g, b } => color::Rgb(r, g, b).fg_string().into(),
+        Color::Ans256 { index } => color::AnsiValue(index).fg_string().into(),
     }
 }
 
@@ -220,6 +240,13 @@ fn get_effect(effect: Effect) -> &'static str {
         Effect::Bold => style::Bold.as_ref(),
         Effect::Italic => style::Italic.as_ref(),
         Effect::Underline => style::Underline.as_ref(),
+        Effect::Strikethrough => style::CrossedOut.as_ref(),
+        Effect::Dim => style::Faint.as_ref(),
+        Effect::Reverse => style::Invert.as_ref(),
+        Effect::Blink => style::Blink.as_ref(),
+        // termion has no `Conceal`/SGR-8 constant, so the escape sequence is written directly;
+        // `style::Reset` still clears it like every other effect in this module.
+        Effect::Hidden => "\x1b[8m",
     }
 }
 
@@ -261,3 +288,311 @@ where
     }
     Ok(())
 }
+
+/// Renders a styled string to the given output using `termion`, down-converting its colors to
+/// the given [`ColorDepth`][] first.
+///
+/// Use this instead of [`render`][] when the target terminal does not support 24-bit truecolor.
+///
+/// # Example
+///
+/// ```
+/// use text_style::ColorDepth;
+///
+/// let s = text_style::StyledStr::plain("test").fg(text_style::Color::Rgb { r: 255, g: 0, b: 0 });
+/// text_style::termion::render_with(std::io::stdout(), s, ColorDepth::Ansi256)
+///     .expect("Failed to render string");
+/// ```
+///
+/// [`render`]: fn.render.html
+pub fn render_with<'a>(
+    mut w: impl io::Write,
+    s: impl Into<StyledStr<'a>>,
+    depth: ColorDepth,
+) -> io::Result<()> {
+    let s = s.into();
+    let style = s.style.map(|style| apply_depth(style, depth));
+    write!(w, "{}", TermionStr { s: s.s, style })
+}
+
+/// Renders multiple styled strings to the given output using `termion`, down-converting their
+/// colors to the given [`ColorDepth`][] first.
+///
+/// Use this instead of [`render_iter`][] when the target terminal does not support 24-bit
+/// truecolor.
+///
+/// [`render_iter`]: fn.render_iter.html
+pub fn render_iter_with<'a, I, Iter, S, W>(mut w: W, iter: I, depth: ColorDepth) -> io::Result<()>
+where
+    I: IntoIterator<Item = S, IntoIter = Iter>,
+    Iter: Iterator<Item = S>,
+    S: Into<StyledStr<'a>>,
+    W: io::Write,
+{
+    for s in iter {
+        let s = s.into();
+        let style = s.style.map(|style| apply_depth(style, depth));
+        write!(w, "{}", TermionStr { s: s.s, style })?;
+    }
+    Ok(())
+}
+
+/// Down-converts the colors of a style to the given [`ColorDepth`][].
+fn apply_depth(style: Style, depth: ColorDepth) -> Style {
+    Style {
+        fg: style.fg.map(|color| color.to_depth(depth)),
+        bg: style.bg.map(|color| color.to_depth(depth)),
+        effects: style.effects,
+    }
+}
+
+/// Detects the [`ColorDepth`][] supported by the terminal attached to `stream`.
+///
+/// Returns `None` if `stream` is not connected to a terminal (using [`termion::is_tty`][]) or if
+/// the `NO_COLOR` environment variable is set, meaning no escape sequences should be emitted at
+/// all.  Otherwise, the `COLORTERM` environment variable is checked for `truecolor` or `24bit` to
+/// detect [`ColorDepth::TrueColor`][]; failing that, `TERM` is checked for a `-256color` suffix
+/// to detect [`ColorDepth::Ansi256`][]; otherwise [`ColorDepth::Ansi16`][] is assumed.
+///
+/// [`termion::is_tty`]: https://docs.rs/termion/latest/termion/fn.is_tty.html
+/// [`ColorDepth::TrueColor`]: ../enum.ColorDepth.html#variant.TrueColor
+/// [`ColorDepth::Ansi256`]: ../enum.ColorDepth.html#variant.Ansi256
+/// [`ColorDepth::Ansi16`]: ../enum.ColorDepth.html#variant.Ansi16
+pub fn detect_color_depth(stream: &impl AsRawFd) -> Option<ColorDepth> {
+    if !termion::is_tty(stream) {
+        return None;
+    }
+    if env::var_os("NO_COLOR").is_some() {
+        return None;
+    }
+    if let Ok(colorterm) = env::var("COLORTERM") {
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return Some(ColorDepth::TrueColor);
+        }
+    }
+    if let Ok(term) = env::var("TERM") {
+        if term.ends_with("-256color") {
+            return Some(ColorDepth::Ansi256);
+        }
+    }
+    Some(ColorDepth::Ansi16)
+}
+
+/// Renders a styled string to `w`, automatically detecting the color depth supported by `w` with
+/// [`detect_color_depth`][] and down-converting colors accordingly.
+///
+/// If [`detect_color_depth`][] returns `None` -- `w` is not a terminal, or `NO_COLOR` is set --
+/// the string is written out without any styling at all.
+///
+/// # Example
+///
+/// ```
+/// let s = text_style::StyledStr::plain("test").fg(text_style::Color::Rgb { r: 255, g: 0, b: 0 });
+/// text_style::termion::render_auto(std::io::stdout(), s)
+///     .expect("Failed to render string");
+/// ```
+pub fn render_auto<'a>(
+    mut w: impl io::Write + AsRawFd,
+    s: impl Into<StyledStr<'a>>,
+) -> io::Result<()> {
+    let s = s.into();
+    match detect_color_depth(&w) {
+        Some(depth) => {
+            let style = s.style.map(|style| apply_depth(style, depth));
+            write!(w, "{}", TermionStr { s: s.s, style })
+        }
+        None => w.write_all(s.s.as_bytes()),
+    }
+}
+
+/// Renders multiple styled strings to the given output using `termion`, emitting only the
+/// difference between each piece's style and the previously rendered one instead of a full
+/// `style::Reset` between every piece.
+///
+/// If a piece's style only *adds* attributes relative to the previous piece's style (the same or
+/// a superset of its foreground color, background color and effects), only the newly added
+/// color and effect codes are written.  Otherwise, a [`style::Reset`][] is written before the new
+/// style's full prefix.  A final [`style::Reset`][] is written at the end if the last piece was
+/// styled.  This is the same technique `ansi_term` uses to avoid redundant escape sequences, and
+/// it produces identical visible output to [`render_iter`][].
+///
+/// # Example
+///
+/// ```
+/// let v = vec![
+///     text_style::StyledStr::plain("test").bold(),
+///     text_style::StyledStr::plain(" test2").bold().italic(),
+/// ];
+/// text_style::termion::render_iter_diff(std::io::stdout(), v.into_iter())
+///     .expect("Failed to render string");
+/// ```
+///
+/// [`render_iter`]: fn.render_iter.html
+/// [`style::Reset`]: https://docs.rs/termion/latest/termion/style/struct.Reset.html
+pub fn render_iter_diff<'a, I, Iter, S, W>(mut w: W, iter: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = S, IntoIter = Iter>,
+    Iter: Iterator<Item = S>,
+    S: Into<StyledStr<'a>>,
+    W: io::Write,
+{
+    let mut current: Option<Style> = None;
+    for s in iter {
+        let s = s.into();
+        write_diff(&mut w, current, s.style)?;
+        w.write_all(s.s.as_bytes())?;
+        current = s.style;
+    }
+    if is_styled(current) {
+        write!(w, "{}", style::Reset)?;
+    }
+    Ok(())
+}
+
+/// Writes the escape sequences needed to go from the `current` style to the `next` style.
+fn write_diff(
+    w: &mut impl io::Write,
+    current: Option<Style>,
+    next: Option<Style>,
+) -> io::Result<()> {
+    if current == next {
+        return Ok(());
+    }
+    if only_adds(current, next) {
+        let current = current.unwrap_or_default();
+        let next = next.unwrap_or_default();
+        if let Some(fg) = next.fg {
+            if current.fg != Some(fg) {
+                w.write_all(get_fg(fg).as_bytes())?;
+            }
+        }
+        if let Some(bg) = next.bg {
+            if current.bg != Some(bg) {
+                w.write_all(get_bg(bg).as_bytes())?;
+            }
+        }
+        for effect in (next.effects - current.effects).iter() {
+            w.write_all(get_effect(effect).as_bytes())?;
+        }
+    } else {
+        if is_styled(current) {
+            write!(w, "{}", style::Reset)?;
+        }
+        if let Some(next) = next {
+            if let Some(fg) = next.fg {
+                w.write_all(get_fg(fg).as_bytes())?;
+            }
+            if let Some(bg) = next.bg {
+                w.write_all(get_bg(bg).as_bytes())?;
+            }
+            for effect in next.effects.iter() {
+                w.write_all(get_effect(effect).as_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if `next` only adds attributes relative to `current`, i.e. neither removes nor
+/// changes an existing foreground color, background color or effect.
+fn only_adds(current: Option<Style>, next: Option<Style>) -> bool {
+    let current = current.unwrap_or_default();
+    let next = match next {
+        Some(next) => next,
+        None => return current == Style::default(),
+    };
+    (current.fg.is_none() || current.fg == next.fg)
+        && (current.bg.is_none() || current.bg == next.bg)
+        && current.effects.is_subset(next.effects)
+}
+
+/// Returns `true` if the given style has any visible attribute set, i.e. rendering it required
+/// writing escape sequences that a final `style::Reset` would need to clear.
+fn is_styled(style: Option<Style>) -> bool {
+    match style {
+        Some(style) => style.fg.is_some() || style.bg.is_some() || !style.effects.is_empty(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red() -> Color {
+        Color::Ansi {
+            color: AnsiColor::Red,
+            mode: AnsiMode::Dark,
+        }
+    }
+
+    fn blue() -> Color {
+        Color::Ansi {
+            color: AnsiColor::Blue,
+            mode: AnsiMode::Dark,
+        }
+    }
+
+    fn reset_bytes() -> Vec<u8> {
+        style::Reset.to_string().into_bytes()
+    }
+
+    #[test]
+    fn only_adds_is_true_for_no_op() {
+        let style = Some(Style::new().fg(red()));
+        assert!(only_adds(style, style));
+    }
+
+    #[test]
+    fn only_adds_is_true_when_current_is_plain() {
+        assert!(only_adds(None, Some(Style::new().bold())));
+    }
+
+    #[test]
+    fn only_adds_is_false_when_an_attribute_changes() {
+        let current = Some(Style::new().fg(red()));
+        let next = Some(Style::new().fg(blue()));
+        assert!(!only_adds(current, next));
+    }
+
+    #[test]
+    fn only_adds_is_false_when_an_attribute_is_removed() {
+        assert!(!only_adds(Some(Style::new().bold()), None));
+    }
+
+    #[test]
+    fn write_diff_no_op_writes_nothing() {
+        let style = Some(Style::new().fg(red()));
+        let mut out = Vec::new();
+        write_diff(&mut out, style, style).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn write_diff_add_only_skips_reset() {
+        let current = Some(Style::new().bold());
+        let next = Some(Style::new().bold().italic());
+        let mut out = Vec::new();
+        write_diff(&mut out, current, next).unwrap();
+        assert_eq!(out, get_effect(Effect::Italic).as_bytes());
+    }
+
+    #[test]
+    fn write_diff_replace_emits_reset_and_new_style() {
+        let current = Some(Style::new().fg(red()));
+        let next = Some(Style::new().fg(blue()));
+        let mut out = Vec::new();
+        write_diff(&mut out, current, next).unwrap();
+        let mut expected = reset_bytes();
+        expected.extend_from_slice(get_fg(blue()).as_bytes());
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn write_diff_remove_emits_only_reset() {
+        let current = Some(Style::new().bold());
+        let mut out = Vec::new();
+        write_diff(&mut out, current, None).unwrap();
+        assert_eq!(out, reset_bytes());
+    }
+}